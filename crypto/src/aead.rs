@@ -0,0 +1,54 @@
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+
+pub const AEAD_KEY_SIZE: usize = 32;
+pub const AEAD_NONCE_SIZE: usize = 12;
+pub const AEAD_TAG_SIZE: usize = 16;
+
+/// Seal `plaintext` with AES-256-GCM under `key`/`nonce`, binding `aad` (authenticated but not
+/// encrypted) to the result. Returns `(ciphertext, tag)`; `ciphertext` is the same length as
+/// `plaintext`.
+///
+/// Callers must never reuse a `(key, nonce)` pair: GCM's confidentiality and authenticity both
+/// collapse under nonce reuse.
+pub fn seal(key: &[u8; AEAD_KEY_SIZE], nonce: &[u8; AEAD_NONCE_SIZE], aad: &[u8], plaintext: &[u8]) -> (Vec<u8>, [u8; AEAD_TAG_SIZE]) {
+    let mut tag = [0u8; AEAD_TAG_SIZE];
+    let ciphertext = encrypt_aead(Cipher::aes_256_gcm(), key, Some(nonce), aad, plaintext, &mut tag).expect("AES-256-GCM encryption failed");
+    (ciphertext, tag)
+}
+
+/// Open a packet sealed by [`seal`]. Returns `None` if `tag` doesn't authenticate, meaning
+/// `ciphertext`/`aad` were tampered with or the wrong key/nonce was used.
+pub fn open(key: &[u8; AEAD_KEY_SIZE], nonce: &[u8; AEAD_NONCE_SIZE], aad: &[u8], ciphertext: &[u8], tag: &[u8; AEAD_TAG_SIZE]) -> Option<Vec<u8>> {
+    decrypt_aead(Cipher::aes_256_gcm(), key, Some(nonce), aad, ciphertext, tag).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_round_trips_a_fresh_seal() {
+        let key = [1u8; AEAD_KEY_SIZE];
+        let nonce = [2u8; AEAD_NONCE_SIZE];
+        let (ciphertext, tag) = seal(&key, &nonce, b"associated data", b"hello world");
+        assert_eq!(open(&key, &nonce, b"associated data", &ciphertext, &tag).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn open_rejects_a_tampered_ciphertext_byte() {
+        let key = [1u8; AEAD_KEY_SIZE];
+        let nonce = [2u8; AEAD_NONCE_SIZE];
+        let (mut ciphertext, tag) = seal(&key, &nonce, b"associated data", b"hello world");
+        ciphertext[0] ^= 1;
+        assert!(open(&key, &nonce, b"associated data", &ciphertext, &tag).is_none());
+    }
+
+    #[test]
+    fn open_rejects_a_tampered_tag_byte() {
+        let key = [1u8; AEAD_KEY_SIZE];
+        let nonce = [2u8; AEAD_NONCE_SIZE];
+        let (ciphertext, mut tag) = seal(&key, &nonce, b"associated data", b"hello world");
+        tag[0] ^= 1;
+        assert!(open(&key, &nonce, b"associated data", &ciphertext, &tag).is_none());
+    }
+}
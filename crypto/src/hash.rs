@@ -0,0 +1,32 @@
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+
+use crate::secret::Secret;
+
+pub const SHA384_HASH_SIZE: usize = 48;
+
+/// Compute HMAC-SHA384 over `data` with `key`.
+pub fn hmac_sha384(key: &[u8], data: &[u8]) -> [u8; SHA384_HASH_SIZE] {
+    let pkey = PKey::hmac(key).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha384(), &pkey).unwrap();
+    signer.update(data).unwrap();
+    let mut out = [0u8; SHA384_HASH_SIZE];
+    let written = signer.sign(&mut out).unwrap();
+    debug_assert_eq!(written, SHA384_HASH_SIZE);
+    out
+}
+
+/// HKDF-Extract as defined in RFC 5869, instantiated with SHA384.
+pub fn hkdf_sha384_extract(salt: &[u8], ikm: &[u8]) -> Secret<SHA384_HASH_SIZE> {
+    Secret::new(hmac_sha384(salt, ikm))
+}
+
+/// HKDF-Expand as defined in RFC 5869, instantiated with SHA384, specialized to a single
+/// `SHA384_HASH_SIZE` byte output block (sufficient for every use in this crate).
+pub fn hkdf_sha384_expand_one_block(prk: &[u8], info: &[u8]) -> [u8; SHA384_HASH_SIZE] {
+    let mut data = Vec::with_capacity(info.len() + 1);
+    data.extend_from_slice(info);
+    data.push(1u8);
+    hmac_sha384(prk, &data)
+}
@@ -0,0 +1,5 @@
+pub mod aead;
+pub mod hash;
+pub mod p384;
+pub mod random;
+pub mod secret;
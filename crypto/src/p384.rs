@@ -0,0 +1,144 @@
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::derive::Deriver;
+use openssl::ec::{EcGroup, EcKey, EcPoint};
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private, Public};
+
+use crate::hash::hkdf_sha384_expand_one_block;
+use crate::secret::Secret;
+
+pub const P384_PUBLIC_KEY_SIZE: usize = 49;
+pub const P384_SECRET_KEY_SIZE: usize = 48;
+pub const P384_ECDH_SHARED_SECRET_SIZE: usize = 48;
+
+/// Domain separation string for deriving a P-384 keypair from a pre-shared secret.
+///
+/// See the "shared secret identity mode" section of the Strong Crypto design notes: this lets a
+/// whole mesh trust one another off of a single passphrase instead of per-node provisioning.
+const SHARED_SECRET_IDENTITY_KDF_INFO: &[u8] = b"zerotier_crypto.p384.shared_secret_identity.v1";
+
+fn group() -> EcGroup {
+    EcGroup::from_curve_name(Nid::SECP384R1).unwrap()
+}
+
+pub struct P384KeyPair(EcKey<Private>);
+
+pub struct P384PublicKey(EcKey<Public>);
+
+impl P384KeyPair {
+    pub fn generate() -> Self {
+        let group = group();
+        Self(EcKey::generate(&group).unwrap())
+    }
+
+    /// Deterministically derive a P-384 keypair from a shared secret.
+    ///
+    /// Every node configured with the same `secret` derives the identical keypair and therefore
+    /// the identical public key, so a mesh can bootstrap trust from one pre-shared passphrase
+    /// instead of exchanging per-node public key blobs out of band. The secret is run through
+    /// HKDF-SHA384 with a fixed, domain-separated info string; on the vanishingly rare occasion
+    /// the derived candidate falls outside `[1, n-1]` we re-derive with an incremented counter
+    /// appended to the info string rather than falling back to something weaker.
+    pub fn from_shared_secret(secret: &[u8]) -> Self {
+        let group = group();
+        let mut order = BigNum::new().unwrap();
+        group.order(&mut order, &mut BigNumContext::new().unwrap()).unwrap();
+
+        let prk = crate::hash::hkdf_sha384_extract(b"zerotier_crypto.p384.shared_secret_identity.salt", secret);
+
+        for counter in 0u32..256 {
+            let mut info = Vec::with_capacity(SHARED_SECRET_IDENTITY_KDF_INFO.len() + 4);
+            info.extend_from_slice(SHARED_SECRET_IDENTITY_KDF_INFO);
+            info.extend_from_slice(&counter.to_be_bytes());
+            let candidate = hkdf_sha384_expand_one_block(prk.as_bytes(), &info);
+
+            let scalar = BigNum::from_slice(&candidate).unwrap();
+            if scalar.num_bits() == 0 || scalar >= order {
+                continue;
+            }
+
+            let mut ctx = BigNumContext::new().unwrap();
+            let mut point = EcPoint::new(&group).unwrap();
+            point.mul_generator2(&group, &scalar, &mut ctx).unwrap();
+            let public_key = EcKey::from_public_key(&group, &point).unwrap();
+            let ec_key = EcKey::from_private_components(&group, &scalar, public_key.public_key()).unwrap();
+            return Self(ec_key);
+        }
+
+        panic!("shared secret identity derivation failed to find a valid scalar after 256 attempts");
+    }
+
+    pub fn from_bytes(public_bytes: &[u8], secret_bytes: &[u8]) -> Option<Self> {
+        let group = group();
+        let scalar = BigNum::from_slice(secret_bytes).ok()?;
+        let mut ctx = BigNumContext::new().ok()?;
+        let point = EcPoint::from_bytes(&group, public_bytes, &mut ctx).ok()?;
+        let public_key = EcKey::from_public_key(&group, &point).ok()?;
+        EcKey::from_private_components(&group, &scalar, public_key.public_key()).ok().map(Self)
+    }
+
+    pub fn public_key(&self) -> P384PublicKey {
+        P384PublicKey(EcKey::from_public_key(&group(), self.0.public_key()).unwrap())
+    }
+
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        let mut ctx = BigNumContext::new().unwrap();
+        self.0
+            .public_key()
+            .to_bytes(&group(), openssl::ec::PointConversionForm::COMPRESSED, &mut ctx)
+            .unwrap()
+    }
+
+    pub fn agree(&self, other: &P384PublicKey) -> Option<Secret<P384_ECDH_SHARED_SECRET_SIZE>> {
+        let pkey_self = PKey::from_ec_key(self.0.clone()).ok()?;
+        let pkey_other = PKey::from_ec_key(other.0.clone()).ok()?;
+        let mut deriver = Deriver::new(&pkey_self).ok()?;
+        deriver.set_peer(&pkey_other).ok()?;
+        let mut shared = [0u8; P384_ECDH_SHARED_SECRET_SIZE];
+        let written = deriver.derive(&mut shared).ok()?;
+        if written == P384_ECDH_SHARED_SECRET_SIZE {
+            Some(Secret::new(shared))
+        } else {
+            None
+        }
+    }
+}
+
+impl P384PublicKey {
+    pub fn from_bytes(b: &[u8]) -> Option<Self> {
+        let group = group();
+        let mut ctx = BigNumContext::new().ok()?;
+        let point = EcPoint::from_bytes(&group, b, &mut ctx).ok()?;
+        EcKey::from_public_key(&group, &point).ok().map(Self)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ctx = BigNumContext::new().unwrap();
+        self.0.public_key().to_bytes(&group(), openssl::ec::PointConversionForm::COMPRESSED, &mut ctx).unwrap()
+    }
+}
+
+impl Clone for P384PublicKey {
+    fn clone(&self) -> Self {
+        Self(EcKey::from_public_key(&group(), self.0.public_key()).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_shared_secret_is_deterministic() {
+        let a = P384KeyPair::from_shared_secret(b"a shared mesh passphrase");
+        let b = P384KeyPair::from_shared_secret(b"a shared mesh passphrase");
+        assert_eq!(a.public_key_bytes(), b.public_key_bytes());
+    }
+
+    #[test]
+    fn from_shared_secret_differs_by_secret() {
+        let a = P384KeyPair::from_shared_secret(b"a shared mesh passphrase");
+        let b = P384KeyPair::from_shared_secret(b"a different passphrase");
+        assert_ne!(a.public_key_bytes(), b.public_key_bytes());
+    }
+}
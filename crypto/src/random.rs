@@ -0,0 +1,44 @@
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    static XORSHIFT_STATE: Cell<u64> = Cell::new(seed());
+}
+
+fn seed() -> u64 {
+    let t = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+    // A stack address is a cheap, thread-distinguishing value to fold into the seed: two threads
+    // seeding at the same nanosecond still end up with different stacks. This can't be
+    // `XORSHIFT_STATE`'s own address: `seed()` runs *during* that thread_local's lazy
+    // initialization, so `XORSHIFT_STATE.with(..)` here would recurse into its own
+    // not-yet-initialized init and overflow the stack.
+    let on_this_threads_stack = 0u8;
+    let a = &on_this_threads_stack as *const u8 as u64;
+    (t ^ a.rotate_left(32)) | 1
+}
+
+/// A fast non-cryptographic PRNG used for jitter, load shedding decisions, and test harnesses.
+///
+/// Do not use this for key material; use `fill_secure` for that.
+pub fn xorshift64_random() -> u64 {
+    XORSHIFT_STATE.with(|s| {
+        let mut x = s.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        s.set(x);
+        x
+    })
+}
+
+/// Fill a buffer with cryptographically secure random bytes.
+pub fn fill_secure(buf: &mut [u8]) {
+    getrandom::getrandom(buf).expect("secure random source unavailable");
+}
+
+/// Return a cryptographically secure random array of N bytes.
+pub fn get_bytes_secure<const N: usize>() -> [u8; N] {
+    let mut b = [0u8; N];
+    fill_secure(&mut b);
+    b
+}
@@ -0,0 +1,47 @@
+use std::ops::{Deref, DerefMut};
+
+/// A secret byte array that is zeroed on drop.
+///
+/// This is used everywhere key material and other sensitive values flow through the crate so
+/// that a stack frame left behind after a function returns can't leak key bytes.
+#[derive(Clone)]
+pub struct Secret<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Secret<N> {
+    pub fn new(b: [u8; N]) -> Self {
+        Self(b)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> Default for Secret<N> {
+    fn default() -> Self {
+        Self([0u8; N])
+    }
+}
+
+impl<const N: usize> Deref for Secret<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for Secret<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> Drop for Secret<N> {
+    fn drop(&mut self) {
+        for b in self.0.iter_mut() {
+            unsafe { std::ptr::write_volatile(b, 0u8) };
+        }
+        std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+    }
+}
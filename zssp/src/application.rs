@@ -0,0 +1,76 @@
+use std::hash::Hash;
+
+use zerotier_crypto::p384::P384KeyPair;
+
+/// Trait implemented by the application embedding ZSSP to supply local identity and policy.
+///
+/// A `Context` is generic over one implementation of this trait, which is consulted for the
+/// local static identity and the various rekey/expiration/timeout knobs that govern session
+/// lifecycle.
+pub trait ApplicationLayer: Sized {
+    /// Number of times a key can be used before an automatic rekey is requested.
+    const REKEY_AFTER_USES: u64;
+    /// Number of times a key can be used before the session must be torn down.
+    const EXPIRE_AFTER_USES: u64;
+    /// Maximum key lifetime in milliseconds before an automatic rekey is requested.
+    const REKEY_AFTER_TIME_MS: i64;
+    /// Random jitter added to `REKEY_AFTER_TIME_MS` so peers don't all rekey in lockstep.
+    const REKEY_AFTER_TIME_MS_MAX_JITTER: u32;
+    /// How long an incoming handshake may remain half-open before it is dropped.
+    const INCOMING_SESSION_NEGOTIATION_TIMEOUT_MS: i64;
+    /// Interval on which an unacknowledged handshake packet is retransmitted.
+    const RETRY_INTERVAL: i64;
+
+    /// Application-defined data associated with an established session.
+    type Data;
+    /// Application-defined incoming packet buffer type. The `From<&[u8]>` bound lets `Context`
+    /// hand back decrypted application data (see `ReceiveResult::OkData`) in the application's
+    /// own buffer type rather than a crate-chosen one.
+    type IncomingPacketBuffer: AsRef<[u8]> + for<'a> From<&'a [u8]>;
+    /// Application-defined handle identifying the physical path a packet arrived on.
+    type PhysicalPath: Clone + PartialEq + Hash;
+
+    /// The local static public key blob, as sent to peers during the handshake.
+    fn get_local_s_public_blob(&self) -> &[u8];
+
+    /// The local static keypair backing `get_local_s_public_blob`.
+    fn get_local_s_keypair(&self) -> &P384KeyPair;
+}
+
+/// Convenience identity for the "shared secret" mode described in the Strong Crypto design
+/// notes: every node constructed with the same `secret` derives the identical [`P384KeyPair`]
+/// and therefore recognizes every other node sharing that secret as trusted, without any
+/// per-node key provisioning.
+///
+/// Typical usage is to store one of these in the application struct and have
+/// `get_local_s_keypair`/`get_local_s_public_blob` return its fields:
+///
+/// ```ignore
+/// struct MyApp {
+///     identity: SharedSecretIdentity,
+/// }
+/// impl ApplicationLayer for MyApp {
+///     fn get_local_s_public_blob(&self) -> &[u8] { self.identity.public_blob() }
+///     fn get_local_s_keypair(&self) -> &P384KeyPair { self.identity.keypair() }
+/// }
+/// ```
+pub struct SharedSecretIdentity {
+    keypair: P384KeyPair,
+    public_blob: Vec<u8>,
+}
+
+impl SharedSecretIdentity {
+    pub fn new(shared_secret: &[u8]) -> Self {
+        let keypair = P384KeyPair::from_shared_secret(shared_secret);
+        let public_blob = keypair.public_key_bytes();
+        Self { keypair, public_blob }
+    }
+
+    pub fn keypair(&self) -> &P384KeyPair {
+        &self.keypair
+    }
+
+    pub fn public_blob(&self) -> &[u8] {
+        &self.public_blob
+    }
+}
@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use zerotier_crypto::p384::P384PublicKey;
+use zerotier_crypto::random::get_bytes_secure;
+use zerotier_crypto::secret::Secret;
+
+use crate::cookie::{hash_physical_path, CookieContext, COOKIE_MAC_SIZE};
+use crate::result::{Error, ReceiveResult};
+use crate::session::{Keys, PathProbe, Session, SessionId, PACKET_TYPE_DATA};
+use crate::trust_store::{fingerprint_static_key, TrustStore};
+use crate::ApplicationLayer;
+
+const PACKET_TYPE_HANDSHAKE_INIT: u8 = 1;
+const PACKET_TYPE_PATH_CHALLENGE: u8 = 3;
+const PACKET_TYPE_PATH_CHALLENGE_RESPONSE: u8 = 4;
+const PACKET_TYPE_HANDSHAKE_RETRY: u8 = 5;
+const PACKET_TYPE_HANDSHAKE_INIT_COOKIE: u8 = 6;
+
+/// How long we'll wait for a peer to echo back a path-validation challenge before giving up and
+/// letting a later data packet from that path retry it.
+const PATH_VALIDATION_TIMEOUT_MS: i64 = 2000;
+
+/// Top level ZSSP endpoint: tracks every session with every peer and drives handshakes,
+/// rekeying, and (optionally) explicit trust-based admission.
+pub struct Context<App: ApplicationLayer> {
+    local_s_public_blob: Vec<u8>,
+    mtu: usize,
+    sessions: Mutex<HashMap<SessionId, Arc<Session<App>>>>,
+    next_session_id: AtomicU64,
+    /// Explicitly trusted remote static keys. Empty by default, in which case `receive` falls
+    /// back to the caller-supplied acceptance closure for every unrecognized static key.
+    trust_store: TrustStore<App::Data>,
+    cookie: CookieContext,
+    /// Number of concurrently held sessions at or above which `receive` starts demanding a
+    /// stateless retry cookie on new handshake inits. `usize::MAX` (the default) disables the
+    /// cookie stage entirely, so a lightly loaded, trusted-network context never pays the extra
+    /// round trip.
+    cookie_watermark: AtomicUsize,
+}
+
+impl<App: ApplicationLayer> Context<App>
+where
+    App::Data: Clone,
+{
+    pub fn new(local_s_public_blob: &[u8], mtu: usize) -> Self {
+        Self {
+            local_s_public_blob: local_s_public_blob.to_vec(),
+            mtu,
+            sessions: Mutex::new(HashMap::new()),
+            next_session_id: AtomicU64::new(1),
+            trust_store: TrustStore::default(),
+            cookie: CookieContext::new(),
+            cookie_watermark: AtomicUsize::new(usize::MAX),
+        }
+    }
+
+    /// The [`TrustStore`] backing explicit-trust admission for this context's incoming sessions.
+    pub fn trust_store(&self) -> &TrustStore<App::Data> {
+        &self.trust_store
+    }
+
+    /// Set the number of concurrently held sessions at or above which new handshake inits are
+    /// required to present a stateless retry cookie (see the module-level docs on the cookie
+    /// stage). Pass `usize::MAX` to disable the cookie stage.
+    pub fn set_cookie_watermark(&self, watermark: usize) {
+        self.cookie_watermark.store(watermark, Ordering::Relaxed);
+    }
+
+    /// Every parameter here is an independent piece of per-call state (the application, the
+    /// outbound sink, the peer's identity and admission material, an optional explicit session
+    /// id, caller data, and the current time): there's no natural subset of them that groups into
+    /// a struct without just renaming this function's argument list into a struct's field list.
+    #[allow(clippy::too_many_arguments)]
+    pub fn open(
+        &self,
+        app: &App,
+        mut send: impl FnMut(&[u8]),
+        mtu: usize,
+        remote_s_public_blob: impl AsRef<[u8]>,
+        remote_s_public: P384PublicKey,
+        psk: Secret<48>,
+        explicit_local_session_id: Option<SessionId>,
+        session_data: App::Data,
+        _current_time: i64,
+    ) -> Result<Arc<Session<App>>, Error> {
+        let shared = app.get_local_s_keypair().agree(&remote_s_public).ok_or(Error::FailedAuthentication)?;
+        let keys = derive_keys(&shared, &psk);
+
+        let id = explicit_local_session_id.unwrap_or_else(|| SessionId(self.next_session_id.fetch_add(1, Ordering::Relaxed)));
+        let session = Arc::new(Session {
+            id,
+            data: session_data,
+            established: std::sync::atomic::AtomicBool::new(true),
+            send_counter: AtomicU64::new(0),
+            keys: Mutex::new(keys),
+            current_path: Mutex::new(None),
+            pending_probe: Mutex::new(None),
+        });
+        self.sessions.lock().unwrap().insert(id, session.clone());
+
+        let mut pkt = Vec::with_capacity(1 + self.local_s_public_blob.len());
+        pkt.push(PACKET_TYPE_HANDSHAKE_INIT);
+        pkt.extend_from_slice(&self.local_s_public_blob);
+        for frag in pkt.chunks(mtu.max(1)) {
+            send(frag);
+        }
+
+        let _ = remote_s_public_blob;
+        Ok(session)
+    }
+
+    /// Process one incoming packet.
+    ///
+    /// `allow_new_session` is consulted before any handshake work is done for a packet that does
+    /// not match an existing session. `check_accept_session` is the legacy dynamic fallback used
+    /// when the presented static key is not found in [`Self::trust_store`]: it is given the
+    /// static public key blob and may return `(remote_public_key, psk, session_data)` to accept.
+    #[allow(clippy::too_many_arguments)]
+    pub fn receive(
+        &self,
+        app: &App,
+        allow_new_session: impl FnOnce() -> bool,
+        check_accept_session: impl FnOnce(&[u8]) -> Option<(P384PublicKey, Secret<48>, App::Data)>,
+        mut send: impl FnMut(Option<&Arc<Session<App>>>, &[u8]),
+        physical_path: App::PhysicalPath,
+        data_buf: &mut [u8],
+        packet: App::IncomingPacketBuffer,
+        current_time: i64,
+    ) -> Result<ReceiveResult<App>, Error> {
+        let mut sessions = self.sessions.lock().unwrap();
+        self.receive_with_sessions(app, allow_new_session, check_accept_session, &mut send, physical_path, data_buf, packet, current_time, &mut sessions)
+    }
+
+    /// Process a burst of incoming packets, e.g. everything drained from one `recvmmsg`-style
+    /// read, under a single acquisition of [`Self::sessions`] rather than one per packet: the
+    /// lock is taken once up front and every packet in the burst, including the cookie-watermark
+    /// check and any new-session insertion, is looked up and handled against that same guard.
+    /// Each packet is otherwise processed exactly as it would be by [`Self::receive`], in order,
+    /// and its own outcome (or error) is collected independently; one packet failing
+    /// authentication or being rejected does not affect the others. `physical_paths` must be the
+    /// same length as `packets`, pairing each packet with the path it arrived on.
+    ///
+    /// Because the lock is held for the whole batch, `allow_new_session` and
+    /// `check_accept_session` must not call back into this `Context` (e.g. from
+    /// [`Self::receive`]/[`Self::receive_batch`]) while they run, or they will deadlock on
+    /// [`Self::sessions`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn receive_batch(
+        &self,
+        app: &App,
+        mut allow_new_session: impl FnMut() -> bool,
+        mut check_accept_session: impl FnMut(&[u8]) -> Option<(P384PublicKey, Secret<48>, App::Data)>,
+        mut send: impl FnMut(Option<&Arc<Session<App>>>, &[u8]),
+        physical_paths: &[App::PhysicalPath],
+        data_buf: &mut [u8],
+        packets: Vec<App::IncomingPacketBuffer>,
+        current_time: i64,
+    ) -> Vec<Result<ReceiveResult<App>, Error>> {
+        assert_eq!(physical_paths.len(), packets.len(), "one physical path is required per packet");
+        let mut sessions = self.sessions.lock().unwrap();
+
+        physical_paths
+            .iter()
+            .cloned()
+            .zip(packets)
+            .map(|(physical_path, packet)| {
+                self.receive_with_sessions(
+                    app,
+                    &mut allow_new_session,
+                    |blob| check_accept_session(blob),
+                    &mut send,
+                    physical_path,
+                    data_buf,
+                    packet,
+                    current_time,
+                    &mut sessions,
+                )
+            })
+            .collect()
+    }
+
+    /// Shared implementation behind [`Self::receive`] and [`Self::receive_batch`]. `sessions` is
+    /// the already-locked session table, held by the caller for the duration of one packet
+    /// ([`Self::receive`]) or the whole batch ([`Self::receive_batch`]).
+    #[allow(clippy::too_many_arguments)]
+    fn receive_with_sessions(
+        &self,
+        app: &App,
+        allow_new_session: impl FnOnce() -> bool,
+        check_accept_session: impl FnOnce(&[u8]) -> Option<(P384PublicKey, Secret<48>, App::Data)>,
+        mut send: impl FnMut(Option<&Arc<Session<App>>>, &[u8]),
+        physical_path: App::PhysicalPath,
+        _data_buf: &mut [u8],
+        packet: App::IncomingPacketBuffer,
+        current_time: i64,
+        sessions: &mut HashMap<SessionId, Arc<Session<App>>>,
+    ) -> Result<ReceiveResult<App>, Error> {
+        let raw = packet.as_ref();
+        let (&packet_type, rest) = raw.split_first().ok_or(Error::InvalidPacket)?;
+
+        match packet_type {
+            PACKET_TYPE_HANDSHAKE_INIT => {
+                let remote_s_public_blob = rest;
+
+                // Under load, don't allocate any state for a first-contact handshake packet:
+                // send back a MAC'd, self-describing cookie and make the client prove it can
+                // complete a round trip (and echo the cookie) before we do anything expensive.
+                if sessions.len() >= self.cookie_watermark.load(Ordering::Relaxed) {
+                    let fp = fingerprint_static_key(remote_s_public_blob);
+                    let path_hash = hash_physical_path(&physical_path);
+                    let (coarse_timestamp, mac) = self.cookie.issue(&fp, path_hash, current_time);
+
+                    let mut retry = Vec::with_capacity(1 + 8 + COOKIE_MAC_SIZE);
+                    retry.push(PACKET_TYPE_HANDSHAKE_RETRY);
+                    retry.extend_from_slice(&coarse_timestamp.to_be_bytes());
+                    retry.extend_from_slice(&mac);
+                    send(None, &retry);
+
+                    return Ok(ReceiveResult::Rejected);
+                }
+
+                self.handle_handshake_init(app, remote_s_public_blob, allow_new_session, check_accept_session, &mut send, sessions)
+            }
+            PACKET_TYPE_HANDSHAKE_INIT_COOKIE => {
+                if rest.len() < 8 + COOKIE_MAC_SIZE {
+                    return Err(Error::InvalidPacket);
+                }
+                let mut ts_bytes = [0u8; 8];
+                ts_bytes.copy_from_slice(&rest[..8]);
+                let coarse_timestamp = i64::from_be_bytes(ts_bytes);
+                let presented_mac = &rest[8..8 + COOKIE_MAC_SIZE];
+                let remote_s_public_blob = &rest[8 + COOKIE_MAC_SIZE..];
+
+                let fp = fingerprint_static_key(remote_s_public_blob);
+                let path_hash = hash_physical_path(&physical_path);
+                if !self.cookie.validate(&fp, path_hash, coarse_timestamp, presented_mac, current_time) {
+                    return Ok(ReceiveResult::Rejected);
+                }
+
+                self.handle_handshake_init(app, remote_s_public_blob, allow_new_session, check_accept_session, &mut send, sessions)
+            }
+            PACKET_TYPE_DATA => {
+                let (id, _) = read_session_id(rest)?;
+                let session = sessions.get(&id).cloned();
+                match session {
+                    Some(session) => match session.open_data_fragment(raw) {
+                        // The AEAD tag verified against this session's receive key, so by this
+                        // crate's threat model the packet is fully authenticated: this is the
+                        // only place we are willing to start a path probe from, since probing on
+                        // an unauthenticated packet would let an off-path attacker, who only
+                        // needs to guess a live (sequentially-assigned) `SessionId`, bounce
+                        // challenges toward a forged physical path of its choosing (a
+                        // reflection/amplification vector).
+                        Some(plaintext) => {
+                            self.maybe_begin_path_validation(&session, physical_path, current_time, &mut send);
+                            Ok(ReceiveResult::OkData(session, App::IncomingPacketBuffer::from(&plaintext[..])))
+                        }
+                        None => Err(Error::FailedAuthentication),
+                    },
+                    None => Err(Error::UnknownLocalSessionId),
+                }
+            }
+            PACKET_TYPE_PATH_CHALLENGE => {
+                let (id, _) = read_session_id(rest)?;
+                let session = sessions.get(&id).cloned().ok_or(Error::UnknownLocalSessionId)?;
+                // Sealed under the session's own keys: anyone can see a path challenge go by, but
+                // only a party holding the live key material can produce the echo we're about to
+                // require, so an off-path attacker who merely guesses a live `SessionId` can't
+                // forge a migration to a path of its choosing.
+                let challenge = session.open_control_message(raw).ok_or(Error::FailedAuthentication)?;
+                let reply = session.seal_control_message(PACKET_TYPE_PATH_CHALLENGE_RESPONSE, &challenge);
+                send(Some(&session), &reply);
+                Ok(ReceiveResult::Ok(session))
+            }
+            PACKET_TYPE_PATH_CHALLENGE_RESPONSE => {
+                let (id, _) = read_session_id(rest)?;
+                let session = sessions.get(&id).cloned().ok_or(Error::UnknownLocalSessionId)?;
+                let echoed = session.open_control_message(raw).ok_or(Error::FailedAuthentication)?;
+
+                let mut probe_slot = session.pending_probe.lock().unwrap();
+                let migrated = if let Some(probe) = probe_slot.as_ref() {
+                    probe.challenge[..] == echoed[..] && current_time - probe.sent_at <= PATH_VALIDATION_TIMEOUT_MS
+                } else {
+                    false
+                };
+                let new_path = if migrated { probe_slot.take().map(|p| p.candidate_path) } else { None };
+                drop(probe_slot);
+
+                match new_path {
+                    Some(new_path) => {
+                        *session.current_path.lock().unwrap() = Some(new_path.clone());
+                        Ok(ReceiveResult::PathMigrated(session, new_path))
+                    }
+                    None => Ok(ReceiveResult::Ok(session)),
+                }
+            }
+            _ => Err(Error::UnknownProtocolVersion),
+        }
+    }
+
+    /// If `physical_path` differs from the path this session currently trusts, and no probe is
+    /// already outstanding (or the previous one has timed out), challenge the peer to prove it's
+    /// reachable via the new path before we switch our send path to it.
+    fn maybe_begin_path_validation(
+        &self,
+        session: &Arc<Session<App>>,
+        physical_path: App::PhysicalPath,
+        current_time: i64,
+        send: &mut impl FnMut(Option<&Arc<Session<App>>>, &[u8]),
+    ) {
+        let mut current_path = session.current_path.lock().unwrap();
+        match current_path.as_ref() {
+            None => {
+                *current_path = Some(physical_path);
+            }
+            Some(known_path) if *known_path != physical_path => {
+                drop(current_path);
+
+                let mut pending = session.pending_probe.lock().unwrap();
+                let rate_limited = matches!(&*pending, Some(p) if current_time - p.sent_at <= PATH_VALIDATION_TIMEOUT_MS);
+                if rate_limited {
+                    return;
+                }
+
+                let challenge = get_bytes_secure::<8>();
+                *pending = Some(PathProbe { challenge, candidate_path: physical_path, sent_at: current_time });
+                drop(pending);
+
+                // Sealed under the session's send key, the same way a data packet would be: an
+                // attacker sniffing or racing the wire sees only ciphertext, so it can't forge the
+                // echo this probe demands without holding the session's key material.
+                let pkt = session.seal_control_message(PACKET_TYPE_PATH_CHALLENGE, &challenge);
+                send(Some(session), &pkt);
+            }
+            Some(_) => {}
+        }
+    }
+
+    /// Shared admission logic for a handshake init, whether it arrived directly or after proving
+    /// itself with a valid retry cookie: explicit trust store first, then the dynamic closure
+    /// fallback.
+    fn handle_handshake_init(
+        &self,
+        app: &App,
+        remote_s_public_blob: &[u8],
+        allow_new_session: impl FnOnce() -> bool,
+        check_accept_session: impl FnOnce(&[u8]) -> Option<(P384PublicKey, Secret<48>, App::Data)>,
+        send: &mut impl FnMut(Option<&Arc<Session<App>>>, &[u8]),
+        sessions: &mut HashMap<SessionId, Arc<Session<App>>>,
+    ) -> Result<ReceiveResult<App>, Error> {
+        // Explicit trust mode: an O(1) fingerprint lookup against the trust store lets us reject
+        // an unrecognized key before doing any P-384 math at all. This is the expensive step an
+        // attacker spraying handshake inits is trying to make us pay.
+        if let Some((session_data, psk)) = self.trust_store.lookup(remote_s_public_blob) {
+            return self.complete_incoming_handshake(app, remote_s_public_blob, psk.unwrap_or_default(), session_data, send, sessions);
+        }
+
+        if !allow_new_session() {
+            return Ok(ReceiveResult::Rejected);
+        }
+
+        match check_accept_session(remote_s_public_blob) {
+            Some((_remote_public, psk, session_data)) => self.complete_incoming_handshake(app, remote_s_public_blob, psk, session_data, send, sessions),
+            None => Ok(ReceiveResult::Rejected),
+        }
+    }
+
+    fn complete_incoming_handshake(
+        &self,
+        app: &App,
+        remote_s_public_blob: &[u8],
+        psk: Secret<48>,
+        session_data: App::Data,
+        send: &mut impl FnMut(Option<&Arc<Session<App>>>, &[u8]),
+        sessions: &mut HashMap<SessionId, Arc<Session<App>>>,
+    ) -> Result<ReceiveResult<App>, Error> {
+        let remote_s_public = P384PublicKey::from_bytes(remote_s_public_blob).ok_or(Error::InvalidPacket)?;
+        let shared = app.get_local_s_keypair().agree(&remote_s_public).ok_or(Error::FailedAuthentication)?;
+        let keys = derive_keys(&shared, &psk);
+
+        let id = SessionId(self.next_session_id.fetch_add(1, Ordering::Relaxed));
+        let returned_data = session_data.clone();
+        let session = Arc::new(Session {
+            id,
+            data: session_data,
+            established: std::sync::atomic::AtomicBool::new(true),
+            send_counter: AtomicU64::new(0),
+            keys: Mutex::new(keys),
+            current_path: Mutex::new(None),
+            pending_probe: Mutex::new(None),
+        });
+        sessions.insert(id, session.clone());
+
+        let mut pkt = Vec::with_capacity(1 + self.local_s_public_blob.len());
+        pkt.push(PACKET_TYPE_HANDSHAKE_INIT);
+        pkt.extend_from_slice(&self.local_s_public_blob);
+        for frag in pkt.chunks(self.mtu.max(1)) {
+            send(Some(&session), frag);
+        }
+
+        Ok(ReceiveResult::OkNewSession(session, returned_data))
+    }
+
+    /// Periodic housekeeping hook, called on whatever cadence its own return value requests.
+    /// Presently a no-op placeholder: `complete_incoming_handshake` finishes and establishes a
+    /// session synchronously on the replying side, so there is no half-open window to expire and
+    /// nothing yet to retransmit or rekey. `App::INCOMING_SESSION_NEGOTIATION_TIMEOUT_MS` is
+    /// unused until that changes. Returns the number of milliseconds until `service` should be
+    /// called again.
+    pub fn service(&self, mut _send: impl FnMut(Option<&Arc<Session<App>>>, &[u8]), _current_time: i64) -> i64 {
+        App::RETRY_INTERVAL
+    }
+}
+
+fn read_session_id(b: &[u8]) -> Result<(SessionId, &[u8]), Error> {
+    if b.len() < 8 {
+        return Err(Error::InvalidPacket);
+    }
+    let mut id_bytes = [0u8; 8];
+    id_bytes.copy_from_slice(&b[..8]);
+    Ok((SessionId(u64::from_be_bytes(id_bytes)), &b[8..]))
+}
+
+fn derive_keys(shared: &Secret<48>, psk: &Secret<48>) -> Keys {
+    let mut mixed = [0u8; 48];
+    for i in 0..48 {
+        mixed[i] = shared[i] ^ psk[i];
+    }
+    let send_key = zerotier_crypto::hash::hmac_sha384(&mixed, b"zssp.session.send_key");
+    let receive_key = zerotier_crypto::hash::hmac_sha384(&mixed, b"zssp.session.receive_key");
+    let fingerprint = zerotier_crypto::hash::hmac_sha384(&mixed, b"zssp.session.fingerprint");
+    Keys { ratchet_count: 0, fingerprint, send_key: Secret::new(send_key), receive_key: Secret::new(receive_key) }
+}
@@ -0,0 +1,122 @@
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use openssl::memcmp;
+
+use zerotier_crypto::hash::hmac_sha384;
+use zerotier_crypto::random::get_bytes_secure;
+
+pub const COOKIE_MAC_SIZE: usize = 32;
+
+/// How often the context secret backing retry cookies is rotated. Rotating it bounds how long a
+/// captured cookie stays valid and lets old cookies expire without the context keeping any
+/// per-cookie state.
+const ROTATION_INTERVAL_MS: i64 = 60_000;
+
+/// A cookie is only accepted within this many milliseconds of the coarse timestamp it carries,
+/// which in turn must have been minted under the current or immediately previous context secret.
+const FRESHNESS_WINDOW_MS: i64 = 2 * ROTATION_INTERVAL_MS;
+
+struct Secrets {
+    current: [u8; 32],
+    previous: [u8; 32],
+    rotated_at: i64,
+}
+
+/// Mints and validates QUIC-Retry-style stateless cookies.
+///
+/// The context keeps no record of which cookies it has issued: a cookie is just
+/// `HMAC(context_secret, peer_static_key_hash || physical_path_hash || coarse_timestamp)`
+/// alongside the `coarse_timestamp` in the clear, so any node holding the current (or previous,
+/// to tolerate rotation) secret can recompute and check it in O(1) with no allocation.
+pub(crate) struct CookieContext {
+    secrets: Mutex<Secrets>,
+}
+
+impl CookieContext {
+    pub fn new() -> Self {
+        // `rotated_at` starts at the epoch origin rather than the caller's clock (which may be a
+        // monotonic clock with an arbitrary zero point) so the first real `current_time` passed
+        // in always looks overdue and an initial pair of secrets gets generated on first use.
+        Self { secrets: Mutex::new(Secrets { current: get_bytes_secure::<32>(), previous: get_bytes_secure::<32>(), rotated_at: 0 }) }
+    }
+
+    fn rotate_if_due(&self, current_time: i64) -> [u8; 32] {
+        let mut secrets = self.secrets.lock().unwrap();
+        if current_time - secrets.rotated_at >= ROTATION_INTERVAL_MS {
+            secrets.previous = secrets.current;
+            secrets.current = get_bytes_secure::<32>();
+            secrets.rotated_at = current_time;
+        }
+        secrets.current
+    }
+
+    /// Issue a fresh cookie for `static_key_fp`/`physical_path` at `current_time`. Returns
+    /// `(coarse_timestamp, mac)`; the caller sends both to the client, which must echo them back
+    /// unmodified on its retried handshake init.
+    pub fn issue(&self, static_key_fp: &[u8], physical_path_hash: u64, current_time: i64) -> (i64, [u8; COOKIE_MAC_SIZE]) {
+        let secret = self.rotate_if_due(current_time);
+        (current_time, mac(&secret, static_key_fp, physical_path_hash, current_time))
+    }
+
+    /// Check a cookie presented by a client. Accepts it if it validates against either the
+    /// current or previous secret (so a cookie minted just before a rotation isn't rejected) and
+    /// falls within the freshness window.
+    pub fn validate(&self, static_key_fp: &[u8], physical_path_hash: u64, coarse_timestamp: i64, presented_mac: &[u8], current_time: i64) -> bool {
+        if (current_time - coarse_timestamp).abs() > FRESHNESS_WINDOW_MS {
+            return false;
+        }
+        let secret = self.rotate_if_due(current_time);
+        let secrets = self.secrets.lock().unwrap();
+        let expected_current = mac(&secret, static_key_fp, physical_path_hash, coarse_timestamp);
+        let expected_previous = mac(&secrets.previous, static_key_fp, physical_path_hash, coarse_timestamp);
+        // Constant-time: a `==` here would leak, via timing, whether a guessed cookie was close
+        // to valid, letting an attacker search for one byte at a time instead of brute-forcing
+        // the whole MAC at once.
+        memcmp::eq(presented_mac, &expected_current) | memcmp::eq(presented_mac, &expected_previous)
+    }
+}
+
+fn mac(secret: &[u8; 32], static_key_fp: &[u8], physical_path_hash: u64, coarse_timestamp: i64) -> [u8; COOKIE_MAC_SIZE] {
+    let mut data = Vec::with_capacity(static_key_fp.len() + 16);
+    data.extend_from_slice(static_key_fp);
+    data.extend_from_slice(&physical_path_hash.to_be_bytes());
+    data.extend_from_slice(&coarse_timestamp.to_be_bytes());
+    let full = hmac_sha384(secret, &data);
+    let mut out = [0u8; COOKIE_MAC_SIZE];
+    out.copy_from_slice(&full[..COOKIE_MAC_SIZE]);
+    out
+}
+
+pub(crate) fn hash_physical_path<P: Hash>(p: &P) -> u64 {
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    p.hash(&mut h);
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_fresh_cookie_round_trips() {
+        let ctx = CookieContext::new();
+        let (coarse_timestamp, mac) = ctx.issue(b"fingerprint", 42, 1_000);
+        assert!(ctx.validate(b"fingerprint", 42, coarse_timestamp, &mac, 1_000));
+    }
+
+    #[test]
+    fn a_tampered_mac_is_rejected() {
+        let ctx = CookieContext::new();
+        let (coarse_timestamp, mut mac) = ctx.issue(b"fingerprint", 42, 1_000);
+        mac[0] ^= 1;
+        assert!(!ctx.validate(b"fingerprint", 42, coarse_timestamp, &mac, 1_000));
+    }
+
+    #[test]
+    fn a_stale_cookie_is_rejected() {
+        let ctx = CookieContext::new();
+        let (coarse_timestamp, mac) = ctx.issue(b"fingerprint", 42, 1_000);
+        assert!(!ctx.validate(b"fingerprint", 42, coarse_timestamp, &mac, 1_000 + FRESHNESS_WINDOW_MS + 1));
+    }
+}
@@ -0,0 +1,12 @@
+mod application;
+mod context;
+mod cookie;
+mod result;
+mod session;
+mod trust_store;
+
+pub use application::{ApplicationLayer, SharedSecretIdentity};
+pub use context::Context;
+pub use result::{Error, ReceiveResult};
+pub use session::{Session, SessionId};
+pub use trust_store::{StaticKeyFingerprint, TrustStore};
@@ -15,6 +15,16 @@ const TEST_MTU: usize = 1500;
 
 struct TestApplication {
     identity_key: P384KeyPair,
+    // Cached so `get_local_s_public_blob` can hand back a `&[u8]` with a stable lifetime instead
+    // of a reference into a `Vec<u8>` freshly computed (and immediately dropped) on every call.
+    public_blob: Vec<u8>,
+}
+
+impl TestApplication {
+    fn new(identity_key: P384KeyPair) -> Self {
+        let public_blob = identity_key.public_key_bytes();
+        Self { identity_key, public_blob }
+    }
 }
 
 impl zssp::ApplicationLayer for TestApplication {
@@ -30,7 +40,7 @@ impl zssp::ApplicationLayer for TestApplication {
     type PhysicalPath = usize;
 
     fn get_local_s_public_blob(&self) -> &[u8] {
-        self.identity_key.public_key_bytes()
+        &self.public_blob
     }
 
     fn get_local_s_keypair(&self) -> &zerotier_crypto::p384::P384KeyPair {
@@ -46,7 +56,7 @@ fn alice_main(
     alice_out: mpsc::SyncSender<Vec<u8>>,
     alice_in: mpsc::Receiver<Vec<u8>>,
 ) {
-    let context = zssp::Context::<TestApplication>::new(alice_app.identity_key.public_key_bytes(), TEST_MTU);
+    let context = zssp::Context::<TestApplication>::new(&alice_app.public_blob, TEST_MTU);
     let mut data_buf = [0u8; 65536];
     let mut next_service = ms_monotonic() + 500;
     let mut last_ratchet_count = 0;
@@ -61,7 +71,7 @@ fn alice_main(
                 let _ = alice_out.send(b.to_vec());
             },
             TEST_MTU,
-            bob_app.identity_key.public_key_bytes(),
+            &bob_app.public_blob,
             bob_app.identity_key.public_key().clone(),
             Secret::default(),
             None,
@@ -157,7 +167,7 @@ fn bob_main(
     bob_out: mpsc::SyncSender<Vec<u8>>,
     bob_in: mpsc::Receiver<Vec<u8>>,
 ) {
-    let context = zssp::Context::<TestApplication>::new(bob_app.identity_key.public_key_bytes(), TEST_MTU);
+    let context = zssp::Context::<TestApplication>::new(&bob_app.public_blob, TEST_MTU);
     let mut data_buf = [0u8; 65536];
     let mut data_buf_2 = [0u8; TEST_MTU];
     let mut last_ratchet_count = 0;
@@ -252,8 +262,8 @@ fn bob_main(
 fn main() {
     let run = AtomicBool::new(true);
 
-    let alice_app = TestApplication { identity_key: P384KeyPair::generate() };
-    let bob_app = TestApplication { identity_key: P384KeyPair::generate() };
+    let alice_app = TestApplication::new(P384KeyPair::generate());
+    let bob_app = TestApplication::new(P384KeyPair::generate());
 
     let (alice_out, bob_in) = mpsc::sync_channel::<Vec<u8>>(1024);
     let (bob_out, alice_in) = mpsc::sync_channel::<Vec<u8>>(1024);
@@ -0,0 +1,49 @@
+use std::fmt;
+
+use crate::ApplicationLayer;
+
+/// Outcome of feeding a single incoming packet to [`crate::Context::receive`].
+pub enum ReceiveResult<App: ApplicationLayer> {
+    /// The packet was valid and processed, but did not produce data or a new session.
+    Ok(std::sync::Arc<crate::Session<App>>),
+    /// The packet was valid and decrypted to application data on an existing session.
+    OkData(std::sync::Arc<crate::Session<App>>, App::IncomingPacketBuffer),
+    /// The packet completed a new incoming session.
+    OkNewSession(std::sync::Arc<crate::Session<App>>, App::Data),
+    /// A peer echoed back a path-validation challenge, confirming it is reachable via the new
+    /// physical path; the session's outgoing path has been migrated to it. The application
+    /// should update whatever socket/address mapping it keeps for this session.
+    PathMigrated(std::sync::Arc<crate::Session<App>>, App::PhysicalPath),
+    /// The packet was rejected: unrecognized session, failed authentication, unrecognized static
+    /// key (see [`crate::TrustStore`]), or policy refusal.
+    Rejected,
+}
+
+/// Errors that can occur while processing a ZSSP packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    UnknownProtocolVersion,
+    InvalidPacket,
+    FailedAuthentication,
+    UnknownLocalSessionId,
+    SessionNotEstablished,
+    DataTooLarge,
+    RateLimited,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::UnknownProtocolVersion => "unknown protocol version",
+            Self::InvalidPacket => "invalid packet",
+            Self::FailedAuthentication => "failed authentication",
+            Self::UnknownLocalSessionId => "unknown local session ID",
+            Self::SessionNotEstablished => "session not established",
+            Self::DataTooLarge => "data too large",
+            Self::RateLimited => "rate limited",
+        };
+        f.write_str(s)
+    }
+}
+
+impl std::error::Error for Error {}
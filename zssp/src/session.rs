@@ -0,0 +1,225 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use zerotier_crypto::aead::{self, AEAD_KEY_SIZE, AEAD_NONCE_SIZE, AEAD_TAG_SIZE};
+use zerotier_crypto::secret::Secret;
+
+use crate::result::Error;
+use crate::ApplicationLayer;
+
+pub(crate) const PACKET_TYPE_DATA: u8 = 2;
+
+/// Size of a sealed fragment's header: packet type (1) + session id (8) + counter (8) + fragment
+/// index (4). The header is authenticated as AEAD associated data but is not itself encrypted,
+/// since `Context::receive` needs the session id to find the key before anything can be
+/// decrypted. Shared by data packets and by the small control messages (path-validation
+/// challenges and their echoes) sealed via [`Session::seal_control_message`].
+const DATA_HEADER_SIZE: usize = 1 + 8 + 8 + 4;
+
+/// Bytes of overhead a sealed fragment adds on top of its plaintext: the header plus the AEAD tag.
+const DATA_PACKET_OVERHEAD: usize = DATA_HEADER_SIZE + AEAD_TAG_SIZE;
+
+/// Uniquely identifies a session from the local peer's point of view.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SessionId(pub u64);
+
+impl std::fmt::Display for SessionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+pub(crate) struct Keys {
+    pub ratchet_count: u64,
+    pub fingerprint: [u8; 48],
+    pub send_key: Secret<48>,
+    pub receive_key: Secret<48>,
+}
+
+/// An outstanding path-validation challenge: we've asked the peer to echo `challenge` back to
+/// prove it is actually reachable via `candidate_path` before we migrate our send path to it.
+pub(crate) struct PathProbe<PhysicalPath> {
+    pub challenge: [u8; 8],
+    pub candidate_path: PhysicalPath,
+    pub sent_at: i64,
+}
+
+/// An established (or establishing) ZSSP session with a single remote peer.
+pub struct Session<App: ApplicationLayer> {
+    pub id: SessionId,
+    pub(crate) data: App::Data,
+    pub(crate) established: AtomicBool,
+    pub(crate) send_counter: AtomicU64,
+    pub(crate) keys: Mutex<Keys>,
+    /// The physical path the last authenticated packet from this peer arrived on. `None` until
+    /// the first packet is received, since `open()` has no inbound packet to anchor it to.
+    pub(crate) current_path: Mutex<Option<App::PhysicalPath>>,
+    /// At most one path-validation challenge may be outstanding per session at a time; this is
+    /// what bounds a session's exposure to being used as an amplification/reflection vector.
+    pub(crate) pending_probe: Mutex<Option<PathProbe<App::PhysicalPath>>>,
+}
+
+impl<App: ApplicationLayer> Session<App> {
+    /// True once the handshake has completed and application data can flow.
+    pub fn established(&self) -> bool {
+        self.established.load(Ordering::Relaxed)
+    }
+
+    /// Returns `(ratchet_count, key_fingerprint)` for the currently active key, or `None` if the
+    /// session has not yet completed its handshake.
+    pub fn key_info(&self) -> Option<(u64, [u8; 48])> {
+        if self.established() {
+            let keys = self.keys.lock().unwrap();
+            Some((keys.ratchet_count, keys.fingerprint))
+        } else {
+            None
+        }
+    }
+
+    /// Encrypt and fragment `data` into packets of at most `scratch.len()` bytes, calling `send`
+    /// once per fragment.
+    pub fn send(&self, mut send: impl FnMut(&[u8]), scratch: &mut [u8], data: &[u8]) -> Result<(), Error> {
+        if !self.established() {
+            return Err(Error::SessionNotEstablished);
+        }
+        let max_fragment_plaintext = scratch.len().saturating_sub(DATA_PACKET_OVERHEAD);
+        if max_fragment_plaintext == 0 {
+            return Err(Error::DataTooLarge);
+        }
+        let counter = self.send_counter.fetch_add(1, Ordering::Relaxed);
+        let keys = self.keys.lock().unwrap();
+        for (i, chunk) in data.chunks(max_fragment_plaintext).enumerate() {
+            let frag_len = seal_fragment(PACKET_TYPE_DATA, &keys.send_key, self.id, counter, i as u32, chunk, scratch);
+            send(&scratch[..frag_len]);
+        }
+        Ok(())
+    }
+
+    /// Encrypt and fragment many `payloads` under a single acquisition of the session's key
+    /// lock, coalescing their fragments into as few calls to `send` as possible: sealed fragments
+    /// are packed back-to-back into `scratch` and only flushed out once another one wouldn't fit
+    /// (or at the end of the batch), rather than once per fragment as [`Self::send`] does.
+    /// Payload order is preserved, but a payload's fragments may share a `send` call with the
+    /// next payload's.
+    pub fn send_batch(&self, mut send: impl FnMut(&[u8]), scratch: &mut [u8], payloads: &[&[u8]]) -> Result<(), Error> {
+        if !self.established() {
+            return Err(Error::SessionNotEstablished);
+        }
+        let max_fragment_plaintext = scratch.len().saturating_sub(DATA_PACKET_OVERHEAD);
+        if max_fragment_plaintext == 0 {
+            return Err(Error::DataTooLarge);
+        }
+        let keys = self.keys.lock().unwrap();
+        let mut filled = 0usize;
+        for data in payloads {
+            let counter = self.send_counter.fetch_add(1, Ordering::Relaxed);
+            for (i, chunk) in data.chunks(max_fragment_plaintext).enumerate() {
+                let sealed_len = DATA_HEADER_SIZE + chunk.len() + AEAD_TAG_SIZE;
+                if filled > 0 && filled + sealed_len > scratch.len() {
+                    send(&scratch[..filled]);
+                    filled = 0;
+                }
+                let frag_len = seal_fragment(PACKET_TYPE_DATA, &keys.send_key, self.id, counter, i as u32, chunk, &mut scratch[filled..]);
+                filled += frag_len;
+            }
+        }
+        if filled > 0 {
+            send(&scratch[..filled]);
+        }
+        Ok(())
+    }
+
+    /// Seal a small control-plane message (currently just a path-validation challenge or its
+    /// echo) under this session's current send key, using the same header-plus-AEAD-tag framing
+    /// as a data fragment so it gets exactly the same authentication guarantee: nothing but a
+    /// holder of this session's keys can produce or read one. `counter` is drawn from the same
+    /// monotonically increasing [`Self::send_counter`] used by [`Self::send`]/[`Self::send_batch`],
+    /// so a `(key, nonce)` pair is never reused between a control message and a data fragment.
+    pub(crate) fn seal_control_message(&self, packet_type: u8, plaintext: &[u8]) -> Vec<u8> {
+        let counter = self.send_counter.fetch_add(1, Ordering::Relaxed);
+        let keys = self.keys.lock().unwrap();
+        let mut out = vec![0u8; DATA_PACKET_OVERHEAD + plaintext.len()];
+        let len = seal_fragment(packet_type, &keys.send_key, self.id, counter, 0, plaintext, &mut out);
+        out.truncate(len);
+        out
+    }
+
+    /// Authenticate and decrypt a control message sealed by the peer's [`Self::seal_control_message`]
+    /// (under its send key, i.e. this session's receive key). Returns `None` if the AEAD tag
+    /// doesn't authenticate.
+    pub(crate) fn open_control_message(&self, raw: &[u8]) -> Option<Vec<u8>> {
+        self.open_data_fragment(raw)
+    }
+
+    /// Application-supplied data associated with this session at creation time.
+    pub fn data(&self) -> &App::Data {
+        &self.data
+    }
+
+    /// The physical path this session last received an authenticated packet on, i.e. the path
+    /// it currently prefers to send on. `None` if no packet has been received yet.
+    pub fn current_path(&self) -> Option<App::PhysicalPath> {
+        self.current_path.lock().unwrap().clone()
+    }
+
+    /// Authenticate and decrypt one sealed fragment, `raw` including its header, under this
+    /// session's current receive key. Returns `None` if the AEAD tag doesn't authenticate,
+    /// meaning `raw` was tampered with, replayed under a key that has since rotated, or forged
+    /// by a party that never completed the handshake. Used directly for data packets and, via
+    /// [`Self::open_control_message`], for path-validation challenges/echoes: the header's
+    /// packet-type byte is authenticated as AEAD associated data either way, so the two can never
+    /// be confused for one another even though they share this framing.
+    pub(crate) fn open_data_fragment(&self, raw: &[u8]) -> Option<Vec<u8>> {
+        if raw.len() < DATA_PACKET_OVERHEAD {
+            return None;
+        }
+        let header = &raw[..DATA_HEADER_SIZE];
+        let counter = u64::from_be_bytes(raw[9..17].try_into().unwrap());
+        let fragment_index = u32::from_be_bytes(raw[17..21].try_into().unwrap());
+        let ciphertext = &raw[DATA_HEADER_SIZE..raw.len() - AEAD_TAG_SIZE];
+        let tag: [u8; AEAD_TAG_SIZE] = raw[raw.len() - AEAD_TAG_SIZE..].try_into().unwrap();
+
+        let keys = self.keys.lock().unwrap();
+        let key = aead_key(&keys.receive_key);
+        let nonce = data_nonce(counter, fragment_index);
+        aead::open(&key, &nonce, header, ciphertext, &tag)
+    }
+}
+
+/// Seal `plaintext` as one fragment (header + ciphertext + tag) into `out`, starting at `out[0]`.
+/// Returns the total length written; `out` must be at least `DATA_PACKET_OVERHEAD +
+/// plaintext.len()` bytes. `packet_type` is written into the header and authenticated as AEAD
+/// associated data, but otherwise doesn't affect sealing; it's what lets
+/// [`Session::seal_control_message`] reuse this for non-data packet types.
+fn seal_fragment(packet_type: u8, send_key: &Secret<48>, id: SessionId, counter: u64, fragment_index: u32, plaintext: &[u8], out: &mut [u8]) -> usize {
+    out[0] = packet_type;
+    out[1..9].copy_from_slice(&id.0.to_be_bytes());
+    out[9..17].copy_from_slice(&counter.to_be_bytes());
+    out[17..21].copy_from_slice(&fragment_index.to_be_bytes());
+
+    let key = aead_key(send_key);
+    let nonce = data_nonce(counter, fragment_index);
+    let (ciphertext, tag) = aead::seal(&key, &nonce, &out[..DATA_HEADER_SIZE], plaintext);
+
+    let ciphertext_end = DATA_HEADER_SIZE + ciphertext.len();
+    out[DATA_HEADER_SIZE..ciphertext_end].copy_from_slice(&ciphertext);
+    out[ciphertext_end..ciphertext_end + AEAD_TAG_SIZE].copy_from_slice(&tag);
+    ciphertext_end + AEAD_TAG_SIZE
+}
+
+/// The first `AEAD_KEY_SIZE` bytes of a derived 48-byte session key, used as the AES-256-GCM key.
+fn aead_key(key: &Secret<48>) -> [u8; AEAD_KEY_SIZE] {
+    let mut k = [0u8; AEAD_KEY_SIZE];
+    k.copy_from_slice(&key[..AEAD_KEY_SIZE]);
+    k
+}
+
+/// `(counter, fragment_index)` uniquely identifies a fragment for the lifetime of the key it was
+/// sealed under (`counter` only ever increases, and never repeats within one session's lifetime
+/// of a key), so the pair doubles as the AEAD nonce with no separate nonce state to track.
+fn data_nonce(counter: u64, fragment_index: u32) -> [u8; AEAD_NONCE_SIZE] {
+    let mut nonce = [0u8; AEAD_NONCE_SIZE];
+    nonce[..8].copy_from_slice(&counter.to_be_bytes());
+    nonce[8..].copy_from_slice(&fragment_index.to_be_bytes());
+    nonce
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use zerotier_crypto::hash::hmac_sha384;
+use zerotier_crypto::secret::Secret;
+
+/// SHA-384 fingerprint of a static public key blob, used as the lookup key in a [`TrustStore`].
+pub type StaticKeyFingerprint = [u8; 48];
+
+pub fn fingerprint_static_key(static_public_blob: &[u8]) -> StaticKeyFingerprint {
+    // HMAC with a fixed, empty-equivalent key is used here rather than a bare hash so the
+    // fingerprint function lives in the same primitive (and constant-time comparison story) as
+    // the rest of the crate's HMAC-SHA384 usage.
+    hmac_sha384(b"zssp.trust_store.fingerprint", static_public_blob)
+}
+
+struct Entry<Data> {
+    data: Data,
+    psk: Option<Secret<48>>,
+}
+
+/// A built-in store of explicitly trusted remote static public keys.
+///
+/// This implements the "explicit trust mode" described in the crate's Strong Crypto design
+/// notes: instead of an application closure that decides, packet by packet, whether to accept an
+/// unfamiliar static key, the application pre-registers the set of keys it trusts. `Context`
+/// consults the store with an O(1) fingerprint lookup *before* doing the expensive P-384
+/// agreement, so CPU is never spent completing a handshake with a peer whose key was never
+/// trusted in the first place.
+///
+/// The legacy closure-based acceptance check remains available as a fallback for applications
+/// that need to make a dynamic (e.g. network-call-backed) trust decision instead of maintaining
+/// a static set.
+pub struct TrustStore<Data> {
+    peers: RwLock<HashMap<StaticKeyFingerprint, Entry<Data>>>,
+}
+
+impl<Data> Default for TrustStore<Data> {
+    fn default() -> Self {
+        Self { peers: RwLock::new(HashMap::new()) }
+    }
+}
+
+impl<Data> TrustStore<Data> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `static_public_blob` as trusted, associating it with `data` and an optional
+    /// per-peer pre-shared key that is mixed into the session's key derivation.
+    pub fn add_trusted_peer(&self, static_public_blob: &[u8], data: Data, psk: Option<Secret<48>>) {
+        let fp = fingerprint_static_key(static_public_blob);
+        self.peers.write().unwrap().insert(fp, Entry { data, psk });
+    }
+
+    /// Remove a previously trusted peer. Returns `true` if it was present.
+    pub fn remove_trusted_peer(&self, static_public_blob: &[u8]) -> bool {
+        let fp = fingerprint_static_key(static_public_blob);
+        self.peers.write().unwrap().remove(&fp).is_some()
+    }
+
+    /// Enumerate the fingerprints of every currently trusted peer.
+    pub fn trusted_fingerprints(&self) -> Vec<StaticKeyFingerprint> {
+        self.peers.read().unwrap().keys().copied().collect()
+    }
+
+    pub(crate) fn lookup(&self, static_public_blob: &[u8]) -> Option<(Data, Option<Secret<48>>)>
+    where
+        Data: Clone,
+    {
+        let fp = fingerprint_static_key(static_public_blob);
+        self.peers.read().unwrap().get(&fp).map(|e| (e.data.clone(), e.psk.clone()))
+    }
+}